@@ -0,0 +1,57 @@
+//! Alternative keypair encodings that show up in the wild but aren't understood by
+//! `Keypair::read_from_file`, which only accepts the standard JSON byte-array format.
+
+use anyhow::{anyhow, Result};
+use bip39::{Language, Mnemonic};
+use ed25519_dalek_bip32::{DerivationPath, ExtendedSecretKey};
+use solana_sdk::signature::Keypair;
+
+/// The derivation path `solana-keygen` (and most wallets) use by default.
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
+
+/// Tries every alternative decoder in turn, returning the first match along with a short tag
+/// naming the format that matched, for logging purposes.
+pub fn try_decode(bytes: &[u8], derivation_path: &str) -> Option<(Keypair, &'static str)> {
+    if let Some(kp) = try_base58_secret(bytes) {
+        return Some((kp, "base58"));
+    }
+
+    if let Some(kp) = try_mnemonic(bytes, derivation_path) {
+        return Some((kp, "bip39"));
+    }
+
+    None
+}
+
+/// A raw base58-encoded 64-byte secret key, as produced by some wallet export flows.
+fn try_base58_secret(bytes: &[u8]) -> Option<Keypair> {
+    let text = std::str::from_utf8(bytes).ok()?.trim();
+    let decoded = bs58::decode(text).into_vec().ok()?;
+
+    Keypair::from_bytes(&decoded).ok()
+}
+
+/// A BIP39 mnemonic, derived at `derivation_path` the same way `solana-keygen recover` does.
+fn try_mnemonic(bytes: &[u8], derivation_path: &str) -> Option<Keypair> {
+    let text = std::str::from_utf8(bytes).ok()?.trim();
+    let mnemonic = Mnemonic::parse_in_normalized(Language::English, text).ok()?;
+
+    derive_keypair(&mnemonic, derivation_path).ok()
+}
+
+fn derive_keypair(mnemonic: &Mnemonic, derivation_path: &str) -> Result<Keypair> {
+    let seed = mnemonic.to_seed("");
+    let path: DerivationPath = derivation_path
+        .parse()
+        .map_err(|_| anyhow!("Invalid derivation path: {}", derivation_path))?;
+
+    let derived = ExtendedSecretKey::from_seed(&seed)
+        .and_then(|extended| extended.derive(&path))
+        .map_err(|e| anyhow!("Could not derive key at {}: {}", derivation_path, e))?;
+
+    let mut keypair_bytes = [0u8; 64];
+    keypair_bytes[..32].copy_from_slice(&derived.secret_key.to_bytes());
+    keypair_bytes[32..].copy_from_slice(&derived.public_key().to_bytes());
+
+    Keypair::from_bytes(&keypair_bytes).map_err(|e| anyhow!(e.to_string()))
+}
@@ -1,11 +1,14 @@
 use std::{
     collections::{BTreeMap, BTreeSet},
+    io::{Cursor, Read},
     path::{Path, PathBuf},
 };
 
 use anyhow::Result;
+use bzip2::read::BzDecoder;
 use clap::Parser;
-use futures::FutureExt;
+use flate2::read::GzDecoder;
+use futures::{stream, FutureExt, StreamExt};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     pubkey::Pubkey,
@@ -16,6 +19,16 @@ use solana_sdk::{
 #[macro_use]
 extern crate log;
 
+mod keypair_source;
+mod output;
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum OutputFormat {
+    Human,
+    Json,
+    Csv,
+}
+
 #[derive(Parser)]
 #[command(author, version)]
 pub struct Scraper {
@@ -27,11 +40,50 @@ pub struct Scraper {
     /// Which RPC to use for balance/owner checking (can be specified multiple times)
     #[arg(long, short)]
     rpc: Vec<String>,
+    /// How many in-flight getMultipleAccounts requests to allow at once, across all RPCs
+    #[arg(long, default_value_t = 8)]
+    max_concurrent_requests: usize,
+    /// Derivation path to use when recovering a keypair from a BIP39 seed phrase
+    #[arg(long, default_value = keypair_source::DEFAULT_DERIVATION_PATH)]
+    derivation_path: String,
+    /// How to emit the final results on stdout; log output always goes to stderr regardless
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    output: OutputFormat,
+    /// Suppress results whose balance is below this many SOL on every configured RPC
+    #[arg(long)]
+    min_sol: Option<f64>,
+    /// Suppress results not owned by this program on any configured RPC
+    #[arg(long)]
+    owner: Option<Pubkey>,
 }
 
-pub fn find_nested_dirs(p: &Path, remaining_levels: usize) -> Result<Vec<PathBuf>> {
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Human => write!(f, "human"),
+            Self::Json => write!(f, "json"),
+            Self::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+/// Server-side limit on the number of pubkeys accepted by a single `getMultipleAccounts` call.
+const GET_MULTIPLE_ACCOUNTS_LIMIT: usize = 100;
+
+pub fn find_nested_dirs(
+    p: &Path,
+    remaining_levels: usize,
+    visited: &mut BTreeSet<PathBuf>,
+) -> Result<Vec<PathBuf>> {
+    let canonical = std::fs::canonicalize(p)?;
+
+    if !visited.insert(canonical.clone()) {
+        trace!("Already visited {:?}, skipping", canonical);
+        return Ok(Vec::new());
+    }
+
     let mut ret = Vec::new();
-    ret.push(p.to_owned());
+    ret.push(canonical);
 
     if remaining_levels == 0 {
         return Ok(ret);
@@ -42,7 +94,7 @@ pub fn find_nested_dirs(p: &Path, remaining_levels: usize) -> Result<Vec<PathBuf
         .filter(|entry| entry.path().is_dir());
 
     for entry in path_contents {
-        let mut partial = match find_nested_dirs(&entry.path(), remaining_levels - 1) {
+        let mut partial = match find_nested_dirs(&entry.path(), remaining_levels - 1, visited) {
             Ok(nested_ok) => nested_ok,
             Err(e) => {
                 trace!("Probably not a directory: {:?}", e.to_string());
@@ -56,7 +108,7 @@ pub fn find_nested_dirs(p: &Path, remaining_levels: usize) -> Result<Vec<PathBuf
     Ok(ret)
 }
 
-pub fn find_solana_keypairs(p: &Path) -> Result<Vec<(PathBuf, Pubkey)>> {
+pub fn find_solana_keypairs(p: &Path, derivation_path: &str) -> Result<Vec<(PathBuf, Pubkey)>> {
     let mut ret = Vec::new();
     let files = std::fs::read_dir(p)?
         .filter_map(|r| r.ok())
@@ -69,13 +121,164 @@ pub fn find_solana_keypairs(p: &Path) -> Result<Vec<(PathBuf, Pubkey)>> {
             }
             Err(e) => {
                 trace!("Probably not a keypair, {}", e.to_string());
+
+                if is_archive(&file.path()) {
+                    match find_solana_keypairs_in_archive(&file.path()) {
+                        Ok(mut hits) => ret.append(&mut hits),
+                        Err(e) => trace!("Could not scan archive {:?}: {}", file.path(), e),
+                    }
+                } else if let Ok(bytes) = std::fs::read(file.path()) {
+                    if let Some((kp, format)) = keypair_source::try_decode(&bytes, derivation_path)
+                    {
+                        trace!("Matched {:?} as a {} keypair", file.path(), format);
+                        ret.push((file.path().to_owned(), kp.pubkey()));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(ret)
+}
+
+fn is_archive(p: &Path) -> bool {
+    let name = p.to_string_lossy().to_lowercase();
+    name.ends_with(".tar.gz")
+        || name.ends_with(".tgz")
+        || name.ends_with(".tar.bz2")
+        || name.ends_with(".zip")
+}
+
+/// Streams through a supported archive format, attempting to parse each entry's bytes as a
+/// Solana JSON keypair. Hits are reported with a `PathBuf` of the form `archive!entry`, so the
+/// inner location survives the round trip through the rest of the pipeline.
+fn find_solana_keypairs_in_archive(archive_path: &Path) -> Result<Vec<(PathBuf, Pubkey)>> {
+    let name = archive_path.to_string_lossy().to_lowercase();
+    let file = std::fs::File::open(archive_path)?;
+
+    if name.ends_with(".zip") {
+        return scan_zip(archive_path, file);
+    }
+
+    let reader: Box<dyn Read> = if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Box::new(GzDecoder::new(file))
+    } else if name.ends_with(".tar.bz2") {
+        Box::new(BzDecoder::new(file))
+    } else {
+        return Ok(Vec::new());
+    };
+
+    scan_tar(archive_path, reader)
+}
+
+/// Keypair files are a few hundred bytes at most, so this cap is generous for legitimate entries
+/// while still bounding how much of a single archive entry we'll ever buffer in memory (guards
+/// against zip bombs / accidentally-huge entries inside an otherwise small archive).
+const MAX_ARCHIVE_ENTRY_BYTES: u64 = 1024 * 1024;
+
+fn scan_tar(archive_path: &Path, reader: impl Read) -> Result<Vec<(PathBuf, Pubkey)>> {
+    let mut ret = Vec::new();
+    let mut archive = tar::Archive::new(reader);
+
+    for entry in archive.entries()? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                trace!("Bad tar entry in {:?}: {}", archive_path, e);
+                continue;
+            }
+        };
+
+        let entry_path = match entry.path() {
+            Ok(p) => p.into_owned(),
+            Err(e) => {
+                trace!("Bad tar entry path in {:?}: {}", archive_path, e);
+                continue;
             }
+        };
+
+        let mut bytes = Vec::new();
+        entry
+            .take(MAX_ARCHIVE_ENTRY_BYTES + 1)
+            .read_to_end(&mut bytes)?;
+
+        if bytes.len() as u64 > MAX_ARCHIVE_ENTRY_BYTES {
+            trace!(
+                "Skipping oversized entry {:?}!{:?} (> {} bytes)",
+                archive_path,
+                entry_path,
+                MAX_ARCHIVE_ENTRY_BYTES
+            );
+            continue;
+        }
+
+        try_keypair_from_bytes(&bytes, archive_path, &entry_path, &mut ret);
+    }
+
+    Ok(ret)
+}
+
+fn scan_zip(archive_path: &Path, file: std::fs::File) -> Result<Vec<(PathBuf, Pubkey)>> {
+    let mut ret = Vec::new();
+    let mut archive = zip::ZipArchive::new(file)?;
+
+    for i in 0..archive.len() {
+        let entry = match archive.by_index(i) {
+            Ok(entry) => entry,
+            Err(e) => {
+                trace!("Bad zip entry in {:?}: {}", archive_path, e);
+                continue;
+            }
+        };
+
+        let entry_path = PathBuf::from(entry.name());
+        let mut bytes = Vec::new();
+        entry
+            .take(MAX_ARCHIVE_ENTRY_BYTES + 1)
+            .read_to_end(&mut bytes)?;
+
+        if bytes.len() as u64 > MAX_ARCHIVE_ENTRY_BYTES {
+            trace!(
+                "Skipping oversized entry {:?}!{:?} (> {} bytes)",
+                archive_path,
+                entry_path,
+                MAX_ARCHIVE_ENTRY_BYTES
+            );
+            continue;
         }
+
+        try_keypair_from_bytes(&bytes, archive_path, &entry_path, &mut ret);
     }
 
     Ok(ret)
 }
 
+fn try_keypair_from_bytes(
+    bytes: &[u8],
+    archive_path: &Path,
+    entry_path: &Path,
+    ret: &mut Vec<(PathBuf, Pubkey)>,
+) {
+    match Keypair::read(&mut Cursor::new(bytes)) {
+        Ok(kp) => {
+            let combined = PathBuf::from(format!(
+                "{}!{}",
+                archive_path.display(),
+                entry_path.display()
+            ));
+            ret.push((combined, kp.pubkey()));
+        }
+        Err(e) => {
+            trace!(
+                "Probably not a keypair in {:?}!{:?}: {}",
+                archive_path,
+                entry_path,
+                e.to_string()
+            );
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Scraper::parse();
@@ -85,9 +288,10 @@ async fn main() -> Result<()> {
         .try_init()?;
 
     let mut all_paths = Vec::new();
+    let mut visited_dirs = BTreeSet::new();
 
     for path in cli.paths {
-        let mut paths = find_nested_dirs(&path, cli.depth)?;
+        let mut paths = find_nested_dirs(&path, cli.depth, &mut visited_dirs)?;
         all_paths.append(&mut paths);
     }
 
@@ -95,7 +299,7 @@ async fn main() -> Result<()> {
 
     let mut all_keys = Vec::new();
     for path in all_paths {
-        let mut keys = find_solana_keypairs(&path)?;
+        let mut keys = find_solana_keypairs(&path, &cli.derivation_path)?;
         all_keys.append(&mut keys);
     }
 
@@ -132,33 +336,90 @@ async fn main() -> Result<()> {
         }
     }
 
-    for pubkey in all_keys_dedup.keys() {
-        let metadata_futs = rpc_clients.iter().map(|c| c.get_account(pubkey));
+    let keys: Vec<Pubkey> = all_keys_dedup.keys().cloned().collect();
+    let chunks: Vec<&[Pubkey]> = keys.chunks(GET_MULTIPLE_ACCOUNTS_LIMIT).collect();
 
-        let joined = futures::future::join_all(metadata_futs).await;
+    let lookups = rpc_clients.iter().flat_map(|rpc| {
+        chunks.iter().map(move |chunk| async move {
+            let result = rpc.get_multiple_accounts(chunk).await;
+            (rpc, *chunk, result)
+        })
+    });
 
-        for (rpc, metadata_result) in rpc_clients.iter().zip(joined.into_iter()) {
-            match metadata_result {
-                Ok(meta) => {
-                    info!(
-                        "{} on {}: {} SOL, owned by {}",
-                        pubkey.to_string(),
-                        rpc.url(),
-                        solana_sdk::native_token::lamports_to_sol(meta.lamports),
-                        meta.owner.to_string()
-                    );
-                }
-                Err(e) => {
-                    debug!(
-                        "{} on {}: No data (error: {})",
-                        pubkey.to_string(),
-                        rpc.url(),
-                        e
-                    )
+    let lookup_results = stream::iter(lookups)
+        .buffer_unordered(cli.max_concurrent_requests)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut per_rpc_by_key: BTreeMap<Pubkey, Vec<output::RpcHit>> = BTreeMap::new();
+
+    for (rpc, chunk, result) in lookup_results {
+        match result {
+            Ok(accounts) => {
+                for (pubkey, maybe_account) in chunk.iter().zip(accounts.into_iter()) {
+                    let hit = match &maybe_account {
+                        Some(meta) => {
+                            let sol = solana_sdk::native_token::lamports_to_sol(meta.lamports);
+
+                            info!(
+                                "{} on {}: {} SOL, owned by {}",
+                                pubkey.to_string(),
+                                rpc.url(),
+                                sol,
+                                meta.owner.to_string()
+                            );
+
+                            output::RpcHit {
+                                url: rpc.url(),
+                                lamports: Some(meta.lamports),
+                                sol: Some(sol),
+                                owner: Some(meta.owner.to_string()),
+                            }
+                        }
+                        None => {
+                            debug!("{} on {}: No data", pubkey.to_string(), rpc.url());
+
+                            output::RpcHit {
+                                url: rpc.url(),
+                                lamports: None,
+                                sol: None,
+                                owner: None,
+                            }
+                        }
+                    };
+
+                    per_rpc_by_key.entry(*pubkey).or_default().push(hit);
                 }
             }
+            Err(e) => {
+                warn!(
+                    "Batch lookup of {} keys on {} failed, skipping: {}",
+                    chunk.len(),
+                    rpc.url(),
+                    e
+                )
+            }
         }
     }
 
+    let records: Vec<output::AccountRecord> = all_keys_dedup
+        .into_iter()
+        .map(|(pubkey, paths)| output::AccountRecord {
+            pubkey: pubkey.to_string(),
+            paths: paths
+                .into_iter()
+                .map(|p| p.display().to_string())
+                .collect(),
+            per_rpc: per_rpc_by_key.remove(&pubkey).unwrap_or_default(),
+        })
+        .filter(|record| record.passes_filters(cli.min_sol, cli.owner.as_ref()))
+        .collect();
+
+    match cli.output {
+        OutputFormat::Human => output::write_human(&records)?,
+        OutputFormat::Json => output::write_json(&records)?,
+        OutputFormat::Csv => output::write_csv(&records)?,
+    }
+
     Ok(())
 }
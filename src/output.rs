@@ -0,0 +1,104 @@
+//! Structured serialization of the final scan results, for piping into other tooling.
+
+use anyhow::Result;
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+
+#[derive(Serialize)]
+pub struct RpcHit {
+    pub url: String,
+    pub lamports: Option<u64>,
+    pub sol: Option<f64>,
+    pub owner: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AccountRecord {
+    pub pubkey: String,
+    pub paths: Vec<String>,
+    pub per_rpc: Vec<RpcHit>,
+}
+
+impl AccountRecord {
+    /// A record passes if at least one RPC's view of the account clears `min_sol` and, when an
+    /// `owner` filter is set, at least one RPC reports that owner. Records with no successful
+    /// lookups at all (e.g. no `--rpc` configured) pass both filters vacuously.
+    pub fn passes_filters(&self, min_sol: Option<f64>, owner: Option<&Pubkey>) -> bool {
+        if self.per_rpc.is_empty() {
+            return true;
+        }
+
+        let sol_ok = match min_sol {
+            None => true,
+            Some(threshold) => self
+                .per_rpc
+                .iter()
+                .any(|hit| hit.sol.is_some_and(|sol| sol >= threshold)),
+        };
+
+        let owner_ok = match owner {
+            None => true,
+            Some(expected) => {
+                let expected = expected.to_string();
+                self.per_rpc
+                    .iter()
+                    .any(|hit| hit.owner.as_deref() == Some(expected.as_str()))
+            }
+        };
+
+        sol_ok && owner_ok
+    }
+}
+
+/// Prints the filtered records to stdout in the same shape the lookup loop already logs to
+/// stderr, so that `--min-sol`/`--owner` actually narrow what the user sees by default instead
+/// of only affecting `--output json|csv`.
+pub fn write_human(records: &[AccountRecord]) -> Result<()> {
+    for record in records {
+        println!("{} in {} directories", record.pubkey, record.paths.len());
+
+        for hit in &record.per_rpc {
+            match (hit.sol, &hit.owner) {
+                (Some(sol), Some(owner)) => {
+                    println!("  {}: {} SOL, owned by {}", hit.url, sol, owner)
+                }
+                _ => println!("  {}: no data", hit.url),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn write_json(records: &[AccountRecord]) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(records)?);
+
+    Ok(())
+}
+
+pub fn write_csv(records: &[AccountRecord]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    writer.write_record(["pubkey", "paths", "rpc_url", "lamports", "sol", "owner"])?;
+
+    for record in records {
+        if record.per_rpc.is_empty() {
+            writer.write_record([record.pubkey.as_str(), &record.paths.join(";"), "", "", "", ""])?;
+            continue;
+        }
+
+        for hit in &record.per_rpc {
+            writer.write_record([
+                record.pubkey.as_str(),
+                &record.paths.join(";"),
+                &hit.url,
+                &hit.lamports.map(|l| l.to_string()).unwrap_or_default(),
+                &hit.sol.map(|s| s.to_string()).unwrap_or_default(),
+                hit.owner.as_deref().unwrap_or_default(),
+            ])?;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}